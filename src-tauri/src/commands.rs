@@ -1,15 +1,39 @@
 use serde_json::Value;
-use tauri::State;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+use crate::sidecar::ServerCapabilities;
 use crate::state::AppState;
 
 #[tauri::command]
 pub async fn rpc_call(
+    app: AppHandle,
     state: State<'_, AppState>,
     method: String,
     params: Option<Value>,
 ) -> Result<Value, String> {
-    let mut sidecar = state.sidecar.lock().await;
-    sidecar.call(&method, params).await
+    // Only hold the lock long enough to dispatch the request - the returned future is
+    // self-contained, so concurrent calls don't serialize behind this lock while the
+    // sidecar is working.
+    let (handle, wait) = {
+        let sidecar = state.sidecar.lock().await;
+        if !sidecar.supports(&method).await {
+            return Err(format!("Sidecar does not support method '{method}'"));
+        }
+        sidecar.call(&method, params).await?
+    };
+    // Let the frontend learn the request id up front so it can wire an abort button.
+    let _ = app.emit(
+        "rpc:started",
+        serde_json::json!({"id": handle.id(), "method": method}),
+    );
+    wait.await
+}
+
+#[tauri::command]
+pub async fn rpc_cancel(state: State<'_, AppState>, id: u64) -> Result<(), String> {
+    let sidecar = state.sidecar.lock().await;
+    sidecar.cancel(id).await;
+    Ok(())
 }
 
 #[tauri::command]
@@ -17,3 +41,19 @@ pub async fn sidecar_status(state: State<'_, AppState>) -> Result<bool, String>
     let sidecar = state.sidecar.lock().await;
     Ok(sidecar.is_connected().await)
 }
+
+#[tauri::command]
+pub async fn sidecar_capabilities(
+    state: State<'_, AppState>,
+) -> Result<Option<ServerCapabilities>, String> {
+    let sidecar = state.sidecar.lock().await;
+    Ok(sidecar.capabilities().await)
+}
+
+/// Record a path the user just picked (e.g. from a native file dialog) and hand back
+/// an opaque handle for it. Only the frontend, acting on the user's own selection,
+/// should call this - it's how `fs.readFile` avoids trusting a raw path from the sidecar.
+#[tauri::command]
+pub async fn register_file_handle(state: State<'_, AppState>, path: String) -> Result<String, String> {
+    Ok(state.files.register(PathBuf::from(path)).await)
+}
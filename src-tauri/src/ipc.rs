@@ -1,9 +1,78 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
 
 static REQUEST_ID: AtomicU64 = AtomicU64::new(1);
 
+/// Wire framing for messages exchanged with the sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Framing {
+    /// `Content-Length: <n>\r\n\r\n<n bytes of UTF-8 JSON>`, LSP-style. Tolerates
+    /// embedded newlines and large bodies. The default for new sidecar builds.
+    ContentLength,
+    /// One JSON value per line, terminated by `\n`. Kept for sidecar builds that
+    /// predate header framing.
+    NewlineDelimited,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing::ContentLength
+    }
+}
+
+impl Framing {
+    /// Wrap an already-serialized, newline-terminated JSON line for the wire.
+    pub fn frame(self, line: &str) -> String {
+        match self {
+            Framing::NewlineDelimited => line.to_string(),
+            Framing::ContentLength => {
+                let body = line.trim_end_matches('\n');
+                format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+            }
+        }
+    }
+}
+
+/// Read one message from `reader`, auto-detecting whether it's header-framed
+/// (`Content-Length: ...`) or newline-delimited JSON. Returns `Ok(None)` on EOF.
+pub async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> std::io::Result<Option<String>> {
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(len) = trimmed.strip_prefix("Content-Length:") {
+            let len: usize = len.trim().parse().map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid Content-Length header")
+            })?;
+            // Consume the remaining headers up to the blank line separator.
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).await?;
+                if header.trim_end_matches(['\r', '\n']).is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0u8; len];
+            reader.read_exact(&mut body).await?;
+            return Ok(Some(String::from_utf8_lossy(&body).into_owned()));
+        }
+
+        return Ok(Some(trimmed.to_string()));
+    }
+}
+
 pub fn next_id() -> u64 {
     REQUEST_ID.fetch_add(1, Ordering::Relaxed)
 }
@@ -63,3 +132,110 @@ pub struct JsonRpcNotification {
     pub method: String,
     pub params: Option<Value>,
 }
+
+/// An inbound request from the sidecar asking the host to do something only it can
+/// (read a file, prompt for a secret, confirm an edit), identified by `method` + `id`.
+#[derive(Debug, Deserialize)]
+pub struct JsonRpcCall {
+    #[allow(dead_code)]
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    pub params: Option<Value>,
+}
+
+/// Any line the sidecar can send us over stdout. Tried in this order so each field
+/// combination lands in exactly one variant: a `Call` has both `id` and `method`, a
+/// `Notification` has `method` but no `id`, and a `Response` is whatever is left.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Message {
+    Call(JsonRpcCall),
+    Notification(JsonRpcNotification),
+    Response(JsonRpcResponse),
+}
+
+/// An outbound notification to the sidecar (no id field, no response expected)
+#[derive(Debug, Serialize)]
+pub struct JsonRpcNotificationOut {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl JsonRpcNotificationOut {
+    pub fn new(method: &str, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        let mut json = serde_json::to_string(self).unwrap();
+        json.push('\n');
+        json
+    }
+}
+
+/// Anything the writer task can serialize and write to the sidecar's stdin.
+pub enum Payload {
+    Request(JsonRpcRequest),
+    Notification(JsonRpcNotificationOut),
+    Response(JsonRpcResponseOut),
+}
+
+impl Payload {
+    pub fn to_line(&self) -> String {
+        match self {
+            Payload::Request(request) => request.to_line(),
+            Payload::Notification(notification) => notification.to_line(),
+            Payload::Response(response) => response.to_line(),
+        }
+    }
+}
+
+/// Our reply to a `JsonRpcCall` the sidecar sent us, reusing its id.
+#[derive(Debug, Serialize)]
+pub struct JsonRpcResponseOut {
+    pub jsonrpc: String,
+    pub id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcErrorOut>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonRpcErrorOut {
+    pub code: i64,
+    pub message: String,
+}
+
+impl JsonRpcResponseOut {
+    pub fn success(id: u64, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: u64, code: i64, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(JsonRpcErrorOut { code, message }),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        let mut json = serde_json::to_string(self).unwrap();
+        json.push('\n');
+        json
+    }
+}
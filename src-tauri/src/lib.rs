@@ -4,6 +4,7 @@ mod ipc;
 mod sidecar;
 mod state;
 
+use sidecar::SidecarProcess;
 use state::AppState;
 use tauri::{Manager, State};
 
@@ -17,7 +18,10 @@ pub fn run() {
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             commands::rpc_call,
+            commands::rpc_cancel,
             commands::sidecar_status,
+            commands::sidecar_capabilities,
+            commands::register_file_handle,
         ])
         .setup(|app| {
             let handle = app.handle().clone();
@@ -25,21 +29,41 @@ pub fn run() {
             // Spawn sidecar on app ready
             tauri::async_runtime::spawn(async move {
                 let state: State<'_, AppState> = handle.state();
-                let mut sidecar = state.sidecar.lock().await;
-
-                match sidecar.start(handle.clone()).await {
-                    Ok(()) => {
-                        log::info!("Sidecar started successfully");
-                        // Health check
-                        match sidecar.call("health.check", None).await {
-                            Ok(result) => log::info!("Sidecar health: {:?}", result),
-                            Err(e) => log::error!("Sidecar health check failed: {}", e),
+
+                // Let the sidecar ask the host to read a file the user actually
+                // selected, identified by the opaque handle `register_file_handle`
+                // returned to the frontend - never a raw path the sidecar names itself,
+                // so a buggy or compromised sidecar can't read arbitrary files.
+                let files = state.files.clone();
+                state
+                    .register_handler("fs.readFile", move |params| {
+                        let files = files.clone();
+                        async move {
+                            let handle = params
+                                .as_ref()
+                                .and_then(|p| p.get("handle"))
+                                .and_then(|p| p.as_str())
+                                .ok_or("fs.readFile requires a 'handle' parameter")?;
+
+                            let path = files
+                                .resolve(handle)
+                                .await
+                                .ok_or_else(|| format!("Unknown file handle: {handle}"))?;
+
+                            tokio::fs::read_to_string(&path)
+                                .await
+                                .map(serde_json::Value::String)
+                                .map_err(|e| format!("Failed to read {}: {e}", path.display()))
                         }
-                    }
-                    Err(e) => {
-                        log::error!("Failed to start sidecar: {}", e);
-                    }
-                }
+                    })
+                    .await;
+
+                // Runs for the life of the app: respawns the sidecar with backoff if
+                // it ever disconnects unexpectedly, so a crash doesn't wedge the app.
+                tauri::async_runtime::spawn(SidecarProcess::start(
+                    state.sidecar.clone(),
+                    handle.clone(),
+                ));
             });
 
             Ok(())
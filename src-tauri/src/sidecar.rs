@@ -1,36 +1,227 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command};
-use tokio::sync::{oneshot, Mutex, RwLock};
-use log::info;
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use tauri::{Emitter, Manager};
 
-use crate::ipc::{JsonRpcRequest, JsonRpcResponse, JsonRpcNotification};
+use crate::ipc::{
+    self, Framing, JsonRpcCall, JsonRpcNotificationOut, JsonRpcRequest, JsonRpcResponseOut, Message,
+    Payload,
+};
 
 type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
 
+/// Depth of the writer channel: how many outgoing payloads may queue up before
+/// `send` starts exerting backpressure on callers.
+const WRITER_CHANNEL_CAPACITY: usize = 64;
+
+/// A handler the host registers to answer a `JsonRpcCall` the sidecar sent us.
+pub type Handler =
+    Arc<dyn Fn(Option<Value>) -> Pin<Box<dyn Future<Output = Result<Value, String>> + Send>> + Send + Sync>;
+type HandlerRegistry = Arc<RwLock<HashMap<String, Handler>>>;
+
+/// Grace period given to the sidecar to exit after `shutdown`/`exit` before we kill it.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Delay before the first reconnect attempt after the sidecar disconnects unexpectedly;
+/// doubles after each consecutive failed attempt, capped at `MAX_BACKOFF`.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Signals that the sidecar has disconnected, whether the process died outright or its
+/// stdout pipe closed. Wrapped so either detector can claim the single send - whichever
+/// notices first wins, the other is a no-op.
+type DisconnectSignal = Arc<Mutex<Option<oneshot::Sender<()>>>>;
+
+async fn signal_disconnect(signal: &DisconnectSignal) {
+    if let Some(tx) = signal.lock().await.take() {
+        let _ = tx.send(());
+    }
+}
+
+/// Fail every outstanding `call` with a disconnect error instead of leaving its
+/// `oneshot` sender parked in the pending map, which would otherwise hang the caller
+/// until its own 120s timeout.
+async fn fail_pending(pending: &PendingRequests, reason: &str) {
+    for (_, sender) in pending.lock().await.drain() {
+        let _ = sender.send(Err(format!("SidecarDisconnected: {reason}")));
+    }
+}
+
+/// Capabilities the sidecar advertises in response to `initialize`, mirroring the
+/// LSP `ServerCapabilities` idea: it tells the host what it can actually do.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    /// RPC methods the sidecar is willing to service.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Model providers the sidecar can translate through (e.g. "openai", "anthropic").
+    #[serde(default)]
+    pub model_providers: Vec<String>,
+    /// Whether the sidecar supports glossary management.
+    #[serde(default)]
+    pub glossary: bool,
+    /// Output formats the sidecar can export to.
+    #[serde(default)]
+    pub output_formats: Vec<String>,
+    /// Wire framing the sidecar wants to use for the rest of the session.
+    #[serde(default)]
+    pub framing: Framing,
+}
+
+/// A handle to an in-flight `call`, returned alongside the future that resolves to
+/// its result so the caller can cancel it (e.g. via `SidecarProcess::cancel`) without
+/// having to await the result first.
+#[derive(Debug, Clone, Copy)]
+pub struct CallHandle {
+    id: u64,
+}
+
+impl CallHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
 pub struct SidecarProcess {
-    child: Option<Child>,
-    stdin: Option<tokio::process::ChildStdin>,
+    /// Sender onto the dedicated writer task's channel; cheap to clone, so issuing a
+    /// request never needs to lock the whole `SidecarProcess` for the duration of a call.
+    writer: Arc<RwLock<Option<mpsc::Sender<Payload>>>>,
+    /// Framing used for outgoing messages. Starts at the default (`Content-Length`)
+    /// and is updated once the sidecar's `initialize` response negotiates one.
+    framing: Arc<RwLock<Framing>>,
     pending: PendingRequests,
     connected: Arc<RwLock<bool>>,
+    /// Cleared on disconnect (not just in `stop()`) so `supports()` can't tell a caller
+    /// a method is available while the process behind it is actually dead.
+    capabilities: Arc<RwLock<Option<ServerCapabilities>>>,
+    handlers: HandlerRegistry,
+    /// Asks the current child's supervisor task to shut it down gracefully; consumed
+    /// (and re-created on every `spawn_once`) so a stray send can't affect a later process.
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    /// Resolves once the supervisor task has finished tearing down the child, so `stop`
+    /// can wait for that before reporting the sidecar stopped.
+    shutdown_done_rx: Option<oneshot::Receiver<()>>,
+    /// Set while `stop()` is tearing things down, so the reconnect loop in `start()`
+    /// knows a disconnect was requested rather than a crash to recover from.
+    shutting_down: Arc<RwLock<bool>>,
 }
 
 impl SidecarProcess {
     pub fn new() -> Self {
         Self {
-            child: None,
-            stdin: None,
+            writer: Arc::new(RwLock::new(None)),
+            framing: Arc::new(RwLock::new(Framing::default())),
             pending: Arc::new(Mutex::new(HashMap::new())),
             connected: Arc::new(RwLock::new(false)),
+            capabilities: Arc::new(RwLock::new(None)),
+            handlers: Arc::new(RwLock::new(HashMap::new())),
+            shutdown_tx: None,
+            shutdown_done_rx: None,
+            shutting_down: Arc::new(RwLock::new(false)),
+        }
+    }
+
+    /// Register a handler for a method the sidecar may call back into the host with
+    /// (e.g. reading a user-selected file, prompting for an API key, confirming a
+    /// glossary edit). Replaces any handler already registered for `method`.
+    pub async fn register_handler<F, Fut>(&self, method: &str, handler: F)
+    where
+        F: Fn(Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        let wrapped: Handler = Arc::new(move |params| Box::pin(handler(params)));
+        self.handlers.write().await.insert(method.to_string(), wrapped);
+    }
+
+    /// Keep a sidecar process alive for the life of the app: spawn it, and if it ever
+    /// disconnects without `stop()` having been called, fail every outstanding call
+    /// and respawn it with exponential backoff, re-running the `initialize` handshake
+    /// each time. Runs until `stop()` sets `shutting_down`.
+    pub async fn start(state: Arc<Mutex<SidecarProcess>>, app_handle: tauri::AppHandle) {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0u32;
+
+        loop {
+            let disconnect_rx = {
+                let mut sidecar = state.lock().await;
+                if *sidecar.shutting_down.read().await {
+                    break;
+                }
+                match sidecar.spawn_once(app_handle.clone()).await {
+                    Ok(disconnect_rx) => disconnect_rx,
+                    Err(e) => {
+                        warn!("Failed to start sidecar: {e}");
+                        let _ = app_handle.emit(
+                            "sidecar:status",
+                            serde_json::json!({"connected": false, "error": e}),
+                        );
+                        // A shutdown requested while we were stuck retrying a failing
+                        // spawn (e.g. a missing or crash-looping binary) must still stop
+                        // the retry loop instead of looping on backoff forever.
+                        let shutting_down = *sidecar.shutting_down.read().await;
+                        drop(sidecar);
+                        if shutting_down {
+                            break;
+                        }
+                        if attempt > 0 {
+                            attempt += 1;
+                        }
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        continue;
+                    }
+                }
+            };
+
+            if attempt > 0 {
+                info!("Sidecar reconnected after {attempt} attempt(s)");
+                let _ = app_handle.emit(
+                    "sidecar:status",
+                    serde_json::json!({"connected": true, "reconnected": true}),
+                );
+            }
+            backoff = INITIAL_BACKOFF;
+            attempt = 0;
+
+            // Blocks until the reader hits EOF or the child process exits.
+            let _ = disconnect_rx.await;
+
+            let shutting_down = {
+                let sidecar = state.lock().await;
+                *sidecar.shutting_down.read().await
+            };
+            if shutting_down {
+                break;
+            }
+
+            attempt += 1;
+            warn!("Sidecar disconnected unexpectedly; reconnecting in {backoff:?}");
+            let _ = app_handle.emit(
+                "sidecar:status",
+                serde_json::json!({"connected": false, "reconnecting": true}),
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
         }
     }
 
-    pub async fn start(&mut self, app_handle: tauri::AppHandle) -> Result<(), String> {
+    /// Spawn the sidecar process and wire up its transport. Returns a receiver that
+    /// resolves once this process disconnects (crash or graceful exit), so `start`
+    /// knows when to respawn.
+    async fn spawn_once(
+        &mut self,
+        app_handle: tauri::AppHandle,
+    ) -> Result<oneshot::Receiver<()>, String> {
         // Try to find sidecar - in dev mode, run Python directly
         let mut cmd = if cfg!(debug_assertions) {
             // Dev mode: run Python module directly
@@ -69,51 +260,142 @@ impl SidecarProcess {
         let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
         let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
 
-        self.stdin = Some(stdin);
-        self.child = Some(child);
+        let (writer_tx, mut writer_rx) = mpsc::channel::<Payload>(WRITER_CHANNEL_CAPACITY);
+        *self.writer.write().await = Some(writer_tx.clone());
+
+        // The very first thing we write is `initialize` itself, before any framing has
+        // been negotiated - send it newline-delimited so sidecar builds that predate
+        // Content-Length framing can still read it. `initialize_handshake` below switches
+        // us to the negotiated framing once the sidecar's response tells us what it wants.
+        *self.framing.write().await = Framing::NewlineDelimited;
+
+        let (disconnect_tx, disconnect_rx) = oneshot::channel::<()>();
+        let disconnect_signal: DisconnectSignal = Arc::new(Mutex::new(Some(disconnect_tx)));
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let (done_tx, done_rx) = oneshot::channel::<()>();
+        self.shutdown_tx = Some(shutdown_tx);
+        self.shutdown_done_rx = Some(done_rx);
+
+        let watcher_disconnect = disconnect_signal.clone();
+
+        // Spawn the process supervisor: it's the sole owner of `Child`, so it can both
+        // detect an unrequested exit (crash) and carry out a graceful `stop()` without
+        // the two racing over who gets to call `kill()`.
+        tokio::spawn(async move {
+            let mut child = child;
+            tokio::select! {
+                status = child.wait() => {
+                    info!("Sidecar process exited: {:?}", status);
+                }
+                _ = shutdown_rx => {
+                    match tokio::time::timeout(SHUTDOWN_GRACE, child.wait()).await {
+                        Ok(status) => info!("Sidecar process exited cleanly: {:?}", status),
+                        Err(_) => {
+                            let _ = child.kill().await;
+                            info!("Sidecar did not exit within the grace period; killed");
+                        }
+                    }
+                }
+            }
+            let _ = done_tx.send(());
+            signal_disconnect(&watcher_disconnect).await;
+        });
+
+        let writer_framing = self.framing.clone();
+
+        // Spawn the writer task: it's the sole owner of `ChildStdin`, so callers never
+        // contend on a lock to write - they just push onto `writer_tx`.
+        tokio::spawn(async move {
+            let mut stdin = stdin;
+            while let Some(payload) = writer_rx.recv().await {
+                let framing = *writer_framing.read().await;
+                let framed = framing.frame(&payload.to_line());
+                if let Err(e) = stdin.write_all(framed.as_bytes()).await {
+                    warn!("Failed to write to sidecar stdin: {e}");
+                    break;
+                }
+                if let Err(e) = stdin.flush().await {
+                    warn!("Failed to flush sidecar stdin: {e}");
+                    break;
+                }
+            }
+        });
 
         let pending = self.pending.clone();
         let connected = self.connected.clone();
+        let capabilities = self.capabilities.clone();
+        let writer = self.writer.clone();
+        let handlers = self.handlers.clone();
+        let reader_writer = writer_tx.clone();
         let app = app_handle.clone();
+        let reader_disconnect = disconnect_signal.clone();
 
-        // Spawn stdout reader (JSON-RPC responses + notifications)
+        // Spawn stdout reader (JSON-RPC responses, notifications, and inbound calls)
         tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
+            let mut reader = BufReader::new(stdout);
+            let mut disconnect_reason = "sidecar stdout closed".to_string();
 
-            while let Ok(Some(line)) = lines.next_line().await {
+            loop {
+                let line = match ipc::read_message(&mut reader).await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        disconnect_reason = format!("sidecar stdout read error: {e}");
+                        break;
+                    }
+                };
                 if line.trim().is_empty() {
                     continue;
                 }
 
-                // Try to parse as response (has "id" field)
-                if let Ok(response) = serde_json::from_str::<JsonRpcResponse>(&line) {
-                    if let Some(id) = response.id {
-                        let mut pending = pending.lock().await;
-                        if let Some(sender) = pending.remove(&id) {
-                            let result = if let Some(error) = response.error {
-                                Err(error.to_string())
-                            } else {
-                                Ok(response.result.unwrap_or(Value::Null))
-                            };
-                            let _ = sender.send(result);
+                match serde_json::from_str::<Message>(&line) {
+                    Ok(Message::Response(response)) => {
+                        if let Some(id) = response.id {
+                            let mut pending = pending.lock().await;
+                            if let Some(sender) = pending.remove(&id) {
+                                let result = if let Some(error) = response.error {
+                                    Err(error.to_string())
+                                } else {
+                                    Ok(response.result.unwrap_or(Value::Null))
+                                };
+                                let _ = sender.send(result);
+                            }
                         }
-                        continue;
                     }
-                }
-
-                // Try to parse as notification (no "id" field)
-                if let Ok(notification) = serde_json::from_str::<JsonRpcNotification>(&line) {
-                    // Forward as Tauri event
-                    let event_name = notification.method.replace('.', ":");
-                    if let Some(params) = notification.params {
-                        let _ = app.emit(&event_name, params);
+                    Ok(Message::Notification(notification)) => {
+                        // Forward as Tauri event
+                        let event_name = notification.method.replace('.', ":");
+                        if let Some(params) = notification.params {
+                            let _ = app.emit(&event_name, params);
+                        }
+                    }
+                    Ok(Message::Call(call)) => {
+                        let handlers = handlers.clone();
+                        let writer = reader_writer.clone();
+                        tokio::spawn(async move {
+                            dispatch_call(handlers, writer, call).await;
+                        });
+                    }
+                    Err(e) => {
+                        warn!("Failed to parse message from sidecar: {e}");
                     }
                 }
             }
 
+            // Clear everything a caller could still reach through - not just `connected`
+            // - so a `call()` made in this window fails fast with "Sidecar not started"
+            // instead of queuing onto a writer task whose stdin belongs to a dead process;
+            // calls already in flight fail with `SidecarDisconnected` below.
             *connected.write().await = false;
-            let _ = app.emit("sidecar:status", serde_json::json!({"connected": false}));
+            *capabilities.write().await = None;
+            *writer.write().await = None;
+            fail_pending(&pending, &disconnect_reason).await;
+            let _ = app.emit(
+                "sidecar:status",
+                serde_json::json!({"connected": false, "error": disconnect_reason}),
+            );
+            signal_disconnect(&reader_disconnect).await;
         });
 
         // Spawn stderr reader (logging)
@@ -126,54 +408,173 @@ impl SidecarProcess {
             }
         });
 
+        let capabilities = match self.initialize_handshake().await {
+            Ok(capabilities) => capabilities,
+            Err(e) => {
+                // The process is up but never answered `initialize` - tell its
+                // supervisor to kill it rather than leaking it across retries.
+                if let Some(shutdown_tx) = self.shutdown_tx.take() {
+                    let _ = shutdown_tx.send(());
+                }
+                return Err(e);
+            }
+        };
+        info!("Sidecar capabilities: {:?}", capabilities);
+        *self.framing.write().await = capabilities.framing;
+        *self.capabilities.write().await = Some(capabilities);
+
         *self.connected.write().await = true;
         let _ = app_handle.emit("sidecar:status", serde_json::json!({"connected": true}));
 
         info!("Sidecar process started successfully");
-        Ok(())
+        Ok(disconnect_rx)
     }
 
-    pub async fn call(&mut self, method: &str, params: Option<Value>) -> Result<Value, String> {
-        let stdin = self.stdin.as_mut().ok_or("Sidecar not started")?;
+    /// Send the `initialize` handshake advertising what the host supports, and block
+    /// until the sidecar replies with its own `ServerCapabilities`.
+    async fn initialize_handshake(&self) -> Result<ServerCapabilities, String> {
+        let host_capabilities = serde_json::json!({
+            "modelProviders": ["openai", "anthropic", "google"],
+            "glossary": true,
+            "outputFormats": ["markdown", "plaintext", "epub"],
+            "supportedFraming": ["content-length", "newline-delimited"],
+        });
 
+        let (_, wait) = self.call("initialize", Some(host_capabilities)).await?;
+        let result = wait.await?;
+        serde_json::from_value(result)
+            .map_err(|e| format!("Sidecar returned an invalid initialize response: {e}"))
+    }
+
+    /// Send a request and return a `CallHandle` (for cancellation) alongside a future
+    /// that resolves to the result, mirroring the LSP request/cancel pattern. The
+    /// future owns everything it needs, so callers can drop any lock they took to
+    /// reach `self` before awaiting it - concurrent calls never serialize on one lock.
+    pub async fn call(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<(CallHandle, impl Future<Output = Result<Value, String>> + 'static), String> {
         let request = JsonRpcRequest::new(method, params);
         let id = request.id;
-        let line = request.to_line();
 
         let (tx, rx) = oneshot::channel();
         self.pending.lock().await.insert(id, tx);
 
-        stdin
-            .write_all(line.as_bytes())
-            .await
-            .map_err(|e| format!("Failed to write to sidecar: {e}"))?;
+        self.send(Payload::Request(request)).await?;
 
-        stdin
-            .flush()
-            .await
-            .map_err(|e| format!("Failed to flush sidecar stdin: {e}"))?;
-
-        // Wait for response with timeout
-        match tokio::time::timeout(std::time::Duration::from_secs(120), rx).await {
-            Ok(Ok(result)) => result,
-            Ok(Err(_)) => Err("Response channel closed".to_string()),
-            Err(_) => {
-                self.pending.lock().await.remove(&id);
-                Err("Sidecar call timed out after 120s".to_string())
+        let pending = self.pending.clone();
+        let wait = async move {
+            match tokio::time::timeout(std::time::Duration::from_secs(120), rx).await {
+                Ok(Ok(result)) => result,
+                // The sender was dropped without sending: `cancel(id)` was called.
+                Ok(Err(_)) => Err("Cancelled".to_string()),
+                Err(_) => {
+                    pending.lock().await.remove(&id);
+                    Err("Sidecar call timed out after 120s".to_string())
+                }
             }
+        };
+
+        Ok((CallHandle { id }, wait))
+    }
+
+    /// Abort an in-flight `call`: sends an `rpc.cancel` notification and drops the
+    /// pending sender so the task awaiting the result resolves with `Cancelled`.
+    pub async fn cancel(&self, id: u64) {
+        self.pending.lock().await.remove(&id);
+        if let Err(e) = self
+            .notify("rpc.cancel", Some(serde_json::json!({"id": id})))
+            .await
+        {
+            warn!("Failed to send cancel notification for request {id}: {e}");
         }
     }
 
+    /// Send a notification (no id, no response expected) to the sidecar.
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), String> {
+        self.send(Payload::Notification(JsonRpcNotificationOut::new(method, params)))
+            .await
+    }
+
+    /// Push a payload onto the writer task's channel.
+    async fn send(&self, payload: Payload) -> Result<(), String> {
+        let sender = self.writer.read().await.clone().ok_or("Sidecar not started")?;
+        sender
+            .send(payload)
+            .await
+            .map_err(|_| "Sidecar writer task is no longer running".to_string())
+    }
+
     pub async fn stop(&mut self) {
-        if let Some(mut child) = self.child.take() {
-            let _ = child.kill().await;
-            info!("Sidecar process stopped");
+        // Tell the reconnect loop in `start()` this disconnect was requested, not a
+        // crash, before anything below can trigger one.
+        *self.shutting_down.write().await = true;
+
+        if self.writer.read().await.is_some() {
+            match self.call("shutdown", None).await {
+                Ok((_, wait)) => {
+                    if let Err(e) = wait.await {
+                        info!("Sidecar did not acknowledge shutdown: {e}");
+                    }
+                }
+                Err(e) => info!("Failed to send shutdown request to sidecar: {e}"),
+            }
+            if let Err(e) = self.notify("exit", None).await {
+                info!("Failed to send exit notification to sidecar: {e}");
+            }
+        }
+
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(());
+            if let Some(done_rx) = self.shutdown_done_rx.take() {
+                let _ = tokio::time::timeout(SHUTDOWN_GRACE + Duration::from_secs(1), done_rx).await;
+            }
         }
-        self.stdin = None;
+
+        *self.writer.write().await = None;
+        *self.framing.write().await = Framing::default();
+        *self.capabilities.write().await = None;
         *self.connected.write().await = false;
     }
 
     pub async fn is_connected(&self) -> bool {
         *self.connected.read().await
     }
+
+    /// Whether the sidecar advertised support for `method` during `initialize`.
+    pub async fn supports(&self, method: &str) -> bool {
+        self.capabilities
+            .read()
+            .await
+            .as_ref()
+            .map(|c| c.methods.iter().any(|m| m == method))
+            .unwrap_or(false)
+    }
+
+    pub async fn capabilities(&self) -> Option<ServerCapabilities> {
+        self.capabilities.read().await.clone()
+    }
+}
+
+/// Look up the handler for an inbound call, run it, and push the matching
+/// `JsonRpcResponse` onto the writer channel, reusing the id the sidecar sent.
+async fn dispatch_call(handlers: HandlerRegistry, writer: mpsc::Sender<Payload>, call: JsonRpcCall) {
+    let handler = handlers.read().await.get(&call.method).cloned();
+
+    let response = match handler {
+        Some(handler) => match handler(call.params).await {
+            Ok(result) => JsonRpcResponseOut::success(call.id, result),
+            Err(message) => JsonRpcResponseOut::error(call.id, -32000, message),
+        },
+        None => JsonRpcResponseOut::error(
+            call.id,
+            -32601,
+            format!("Method not found: {}", call.method),
+        ),
+    };
+
+    if writer.send(Payload::Response(response)).await.is_err() {
+        warn!("Failed to send response for call '{}': writer task is gone", call.method);
+    }
 }
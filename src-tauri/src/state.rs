@@ -1,15 +1,55 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use serde_json::Value;
+use tokio::sync::{Mutex, RwLock};
 use crate::sidecar::SidecarProcess;
 
+static FILE_HANDLE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Paths the user has actually picked (e.g. via a native file dialog), keyed by an
+/// opaque handle so the sidecar can ask to read one back without ever naming a raw
+/// filesystem path itself.
+#[derive(Clone, Default)]
+pub struct FileRegistry {
+    paths: Arc<RwLock<HashMap<String, PathBuf>>>,
+}
+
+impl FileRegistry {
+    /// Record a user-selected path and return the opaque handle for it.
+    pub async fn register(&self, path: PathBuf) -> String {
+        let handle = FILE_HANDLE_ID.fetch_add(1, Ordering::Relaxed).to_string();
+        self.paths.write().await.insert(handle.clone(), path);
+        handle
+    }
+
+    pub async fn resolve(&self, handle: &str) -> Option<PathBuf> {
+        self.paths.read().await.get(handle).cloned()
+    }
+}
+
 pub struct AppState {
     pub sidecar: Arc<Mutex<SidecarProcess>>,
+    /// User-selected files the sidecar is allowed to read back via `fs.readFile`.
+    pub files: FileRegistry,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             sidecar: Arc::new(Mutex::new(SidecarProcess::new())),
+            files: FileRegistry::default(),
         }
     }
+
+    /// Register a handler for a method the sidecar may call back into the host with.
+    pub async fn register_handler<F, Fut>(&self, method: &str, handler: F)
+    where
+        F: Fn(Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        self.sidecar.lock().await.register_handler(method, handler).await;
+    }
 }